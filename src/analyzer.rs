@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{ASTNode, BinaryOperator, Node, Span};
+
+/// A coarse type inferred for an expression, used to catch obvious mistakes
+/// before bytecode generation. `Unknown` covers anything the analyzer can't
+/// pin down (function results, parameters, list elements) and is never
+/// treated as an error on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Number,
+    String,
+    Boolean,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    UndefinedVariable { name: String, span: Span },
+    InvalidOperandType { op: BinaryOperator, span: Span },
+    NonBooleanCondition { span: Span },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::UndefinedVariable { name, span } => write!(
+                f,
+                "use of undefined variable `{}` at {}..{}",
+                name,
+                span.offset,
+                span.offset + span.len
+            ),
+            Diagnostic::InvalidOperandType { op, span } => write!(
+                f,
+                "{:?} cannot be applied to a string operand at {}..{}",
+                op,
+                span.offset,
+                span.offset + span.len
+            ),
+            Diagnostic::NonBooleanCondition { span } => write!(
+                f,
+                "condition does not yield a boolean at {}..{}",
+                span.offset,
+                span.offset + span.len
+            ),
+        }
+    }
+}
+
+/// Walks the AST before `BytecodeGenerator::generate` runs, tracking an
+/// inferred `ValueKind` per variable name and collecting diagnostics instead
+/// of failing fast, so every problem in the tree is reported at once.
+pub struct Analyzer {
+    scope: HashMap<String, ValueKind>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Analyzer::new()
+    }
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Analyzer {
+            scope: HashMap::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// One-shot analysis of a whole program, starting from an empty scope.
+    pub fn analyze(program: &[Node]) -> Vec<Diagnostic> {
+        Analyzer::new().analyze_program(program)
+    }
+
+    /// Analyzes `program` against this analyzer's accumulated scope, so a caller
+    /// that re-analyzes input incrementally (e.g. a REPL) sees variables bound
+    /// by earlier calls instead of reporting them as undefined.
+    pub fn analyze_program(&mut self, program: &[Node]) -> Vec<Diagnostic> {
+        self.diagnostics.clear();
+        for statement in program {
+            self.visit(statement);
+        }
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn visit(&mut self, node: &Node) -> ValueKind {
+        match &node.kind {
+            ASTNode::NumberLiteral(_) => ValueKind::Number,
+            ASTNode::StringLiteral(_) => ValueKind::String,
+            ASTNode::BinaryOp(left, op, right) => {
+                let left_kind = self.visit(left);
+                let right_kind = self.visit(right);
+                match op {
+                    BinaryOperator::Subtract
+                    | BinaryOperator::Multiply
+                    | BinaryOperator::Divide => {
+                        if left_kind == ValueKind::String || right_kind == ValueKind::String {
+                            self.diagnostics.push(Diagnostic::InvalidOperandType {
+                                op: *op,
+                                span: node.span,
+                            });
+                        }
+                        ValueKind::Number
+                    }
+                    BinaryOperator::Add => ValueKind::Unknown,
+                    BinaryOperator::Eq
+                    | BinaryOperator::Ne
+                    | BinaryOperator::Lt
+                    | BinaryOperator::Le
+                    | BinaryOperator::Gt
+                    | BinaryOperator::Ge
+                    | BinaryOperator::And
+                    | BinaryOperator::Or => ValueKind::Boolean,
+                }
+            }
+            ASTNode::UnaryOp(_, operand) => {
+                self.visit(operand);
+                ValueKind::Boolean
+            }
+            ASTNode::Variable(name) => match self.scope.get(name) {
+                Some(kind) => *kind,
+                None => {
+                    self.diagnostics.push(Diagnostic::UndefinedVariable {
+                        name: name.clone(),
+                        span: node.span,
+                    });
+                    ValueKind::Unknown
+                }
+            },
+            ASTNode::Assignment(name, value) => {
+                // A function bound by `let`/assignment is registered before its body
+                // is visited, so a call to itself (plain recursion) resolves instead
+                // of being misreported as an undefined variable.
+                if matches!(value.kind, ASTNode::Function(..)) {
+                    self.scope.insert(name.clone(), ValueKind::Unknown);
+                }
+                let kind = self.visit(value);
+                self.scope.insert(name.clone(), kind);
+                kind
+            }
+            ASTNode::Block(statements) => {
+                let mut last_kind = ValueKind::Unknown;
+                for statement in statements {
+                    last_kind = self.visit(statement);
+                }
+                last_kind
+            }
+            ASTNode::If(condition, then_branch, else_branch) => {
+                self.check_condition(condition);
+                self.visit(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.visit(else_branch);
+                }
+                ValueKind::Unknown
+            }
+            ASTNode::While(condition, body) => {
+                self.check_condition(condition);
+                self.visit(body);
+                ValueKind::Unknown
+            }
+            ASTNode::Function(params, body) => {
+                // `scope` is a single flat map shared with the enclosing code, so a
+                // param binding has to be undone after the body is visited — left in
+                // place, it would leak into the surrounding scope the way `Process`'s
+                // per-call-frame scoping (which this analyzer is modeling) never would.
+                let shadowed: Vec<(String, Option<ValueKind>)> = params
+                    .iter()
+                    .map(|param| (param.clone(), self.scope.insert(param.clone(), ValueKind::Unknown)))
+                    .collect();
+                self.visit(body);
+                for (param, previous) in shadowed {
+                    match previous {
+                        Some(kind) => {
+                            self.scope.insert(param, kind);
+                        }
+                        None => {
+                            self.scope.remove(&param);
+                        }
+                    }
+                }
+                ValueKind::Unknown
+            }
+            ASTNode::Call(callee, args) => {
+                for arg in args {
+                    self.visit(arg);
+                }
+                self.visit(callee);
+                ValueKind::Unknown
+            }
+            ASTNode::ListLiteral(elements) => {
+                for element in elements {
+                    self.visit(element);
+                }
+                ValueKind::Unknown
+            }
+            ASTNode::Index(list, index) => {
+                self.visit(list);
+                self.visit(index);
+                ValueKind::Unknown
+            }
+            ASTNode::SetIndex(list, index, value) => {
+                self.visit(list);
+                self.visit(index);
+                self.visit(value)
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Node) {
+        let kind = self.visit(condition);
+        if kind != ValueKind::Boolean && kind != ValueKind::Unknown {
+            self.diagnostics.push(Diagnostic::NonBooleanCondition {
+                span: condition.span,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn diagnostics_for(source: &str) -> Vec<Diagnostic> {
+        let statements = Parser::new(source)
+            .and_then(|mut parser| parser.parse_program())
+            .expect("source should parse");
+        Analyzer::analyze(&statements)
+    }
+
+    #[test]
+    fn flags_use_of_an_undefined_variable() {
+        let diagnostics = diagnostics_for("x;");
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [Diagnostic::UndefinedVariable { name, .. }] if name == "x"
+        ));
+    }
+
+    #[test]
+    fn flags_arithmetic_on_a_string_operand() {
+        let diagnostics = diagnostics_for("\"a\" - 1;");
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [Diagnostic::InvalidOperandType {
+                op: BinaryOperator::Subtract,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn flags_a_non_boolean_if_condition() {
+        let diagnostics = diagnostics_for("if (1) { 2; }");
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [Diagnostic::NonBooleanCondition { .. }]
+        ));
+    }
+
+    #[test]
+    fn accepts_a_recursive_function_referencing_itself() {
+        let diagnostics = diagnostics_for("let fact = function(n) { fact(n - 1); };");
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn does_not_leak_a_parameter_into_the_enclosing_scope() {
+        let diagnostics = diagnostics_for("let f = function(n) { n + 1; }; n;");
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [Diagnostic::UndefinedVariable { name, .. }] if name == "n"
+        ));
+    }
+
+    #[test]
+    fn analyze_program_remembers_bindings_across_calls() {
+        let mut analyzer = Analyzer::new();
+        let first = Parser::new("let x = 1;")
+            .and_then(|mut parser| parser.parse_program())
+            .unwrap();
+        assert!(analyzer.analyze_program(&first).is_empty());
+
+        let second = Parser::new("x;")
+            .and_then(|mut parser| parser.parse_program())
+            .unwrap();
+        assert!(analyzer.analyze_program(&second).is_empty());
+    }
+}
@@ -1,17 +1,62 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(offset: usize, len: usize) -> Self {
+        Span { offset, len }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub kind: ASTNode,
+    pub span: Span,
+}
+
+impl Node {
+    pub fn new(kind: ASTNode, span: Span) -> Self {
+        Node { kind, span }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum ASTNode {
     StringLiteral(String),
     NumberLiteral(f64),
-    BinaryOp(Box<ASTNode>, BinaryOperator, Box<ASTNode>),
+    BinaryOp(Box<Node>, BinaryOperator, Box<Node>),
+    UnaryOp(UnaryOperator, Box<Node>),
     Variable(String),
-    Assignment(String, Box<ASTNode>),
-    If(Box<ASTNode>, Box<ASTNode>, Option<Box<ASTNode>>),
-    While(Box<ASTNode>, Box<ASTNode>),
-    Block(Vec<ASTNode>),
+    Assignment(String, Box<Node>),
+    If(Box<Node>, Box<Node>, Option<Box<Node>>),
+    While(Box<Node>, Box<Node>),
+    Block(Vec<Node>),
+    Function(Vec<String>, Box<Node>),
+    Call(Box<Node>, Vec<Node>),
+    ListLiteral(Vec<Node>),
+    Index(Box<Node>, Box<Node>),
+    SetIndex(Box<Node>, Box<Node>, Box<Node>),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinaryOperator {
     Add,
     Subtract,
     Multiply,
     Divide,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Not,
 }
@@ -0,0 +1,180 @@
+//! Interactive REPL: compiles each input through the lexer/parser/analyzer/generator
+//! pipeline and runs it against a single long-lived `Process`, so `let`-bound
+//! variables persist across inputs. Line editing here is a minimal std-only
+//! reader (history file + multi-line continuation) rather than a real
+//! rustyline-backed one, since this tree has no dependency manifest to pull
+//! rustyline in from.
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, Write};
+
+use es::analyzer::Analyzer;
+use es::ast::{ASTNode, Node, Span};
+use es::generator::BytecodeGenerator;
+use es::instruction::Instruction;
+use es::parser::Parser;
+use es::process::Process;
+
+fn history_path() -> String {
+    match env::var("HOME") {
+        Ok(home) => format!("{}/.es_history", home),
+        Err(_) => ".es_history".to_string(),
+    }
+}
+
+fn brace_balance(source: &str) -> i64 {
+    let mut balance = 0i64;
+    let mut in_string = false;
+    for ch in source.chars() {
+        match ch {
+            '"' => in_string = !in_string,
+            '{' if !in_string => balance += 1,
+            '}' if !in_string => balance -= 1,
+            _ => {}
+        }
+    }
+    balance
+}
+
+fn read_statement<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut buffer = String::new();
+    loop {
+        if buffer.is_empty() {
+            print!("es> ");
+        } else {
+            print!("... ");
+        }
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None); // Ctrl-D / EOF
+        }
+        buffer.push_str(&line);
+
+        if brace_balance(&buffer) <= 0 {
+            return Ok(Some(buffer));
+        }
+    }
+}
+
+fn main() {
+    let mut process = Process::new(0);
+    let mut analyzer = Analyzer::new();
+    let mut generator = BytecodeGenerator::new();
+    let mut last_instructions: Vec<Instruction> = Vec::new();
+
+    let history_file = history_path();
+    let mut history = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&history_file)
+        .ok();
+
+    println!("es repl — :vars, :reset, :dump, Ctrl-D to exit");
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+
+    loop {
+        let statement = match read_statement(&mut input) {
+            Ok(Some(statement)) => statement,
+            Ok(None) => {
+                println!();
+                break;
+            }
+            Err(err) => {
+                eprintln!("input error: {}", err);
+                break;
+            }
+        };
+
+        let trimmed = statement.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        match trimmed {
+            ":vars" => {
+                for (name, value) in process.variables() {
+                    println!("{} = {}", name, value);
+                }
+                continue;
+            }
+            ":reset" => {
+                process = Process::new(0);
+                analyzer = Analyzer::new();
+                generator = BytecodeGenerator::new();
+                last_instructions.clear();
+                println!("state reset");
+                continue;
+            }
+            ":dump" => {
+                for (index, instruction) in last_instructions.iter().enumerate() {
+                    println!("{:04}: {:?}", index, instruction);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some(file) = history.as_mut() {
+            let _ = writeln!(file, "{}", trimmed);
+        }
+
+        let mut parser = match Parser::new(&statement) {
+            Ok(parser) => parser,
+            Err(err) => {
+                eprintln!("parse error: {}", err);
+                continue;
+            }
+        };
+        let statements = match parser.parse_program() {
+            Ok(statements) => statements,
+            Err(err) => {
+                eprintln!("parse error: {}", err);
+                continue;
+            }
+        };
+        if statements.is_empty() {
+            continue;
+        }
+
+        let diagnostics = analyzer.analyze_program(&statements);
+        if !diagnostics.is_empty() {
+            for diagnostic in &diagnostics {
+                eprintln!("analysis error: {}", diagnostic);
+            }
+            continue;
+        }
+
+        let span = Span::new(0, statement.len());
+        let program = Node::new(ASTNode::Block(statements), span);
+
+        let instructions_start = generator.instructions.len();
+        let last_reg = match generator.generate(&program) {
+            Ok(last_reg) => last_reg,
+            Err(err) => {
+                eprintln!("generation error: {}", err);
+                generator.instructions.truncate(instructions_start);
+                continue;
+            }
+        };
+        generator
+            .instructions
+            .push(Instruction::DbgPrintReg(last_reg));
+
+        let new_instructions = generator.instructions[instructions_start..].to_vec();
+        last_instructions = new_instructions.clone();
+        process.extend_program(
+            new_instructions,
+            generator.next_register,
+            generator.function_frames.clone(),
+        );
+
+        if let Err(err) = process.run_program() {
+            eprintln!("{}", err);
+        }
+    }
+}
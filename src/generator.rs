@@ -1,15 +1,47 @@
 use std::collections::HashMap;
+use std::fmt;
 
 use crate::{
-    ast::{ASTNode, BinaryOperator},
+    ast::{ASTNode, BinaryOperator, Node, UnaryOperator},
     instruction::{Instruction, Register},
     value::Value,
 };
 
+/// An error raised while lowering an AST into bytecode; `generate` returns it so
+/// callers can report it as a diagnostic and keep going instead of aborting.
+/// A call to a name the generator hasn't resolved is not one of these errors:
+/// it is lowered to `CallReg` and resolved at runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenError {
+    UndefinedFunction(String),
+    UnsupportedCallee,
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenError::UndefinedFunction(name) => {
+                write!(f, "call to undefined function `{}`", name)
+            }
+            GenError::UnsupportedCallee => {
+                write!(f, "calls are only supported on a named function")
+            }
+        }
+    }
+}
+
 pub struct BytecodeGenerator {
     pub instructions: Vec<Instruction>,
     pub next_register: usize,
     pub variables: HashMap<String, Register>,
+    pub functions: HashMap<String, usize>,
+    pub function_frames: HashMap<usize, usize>,
+}
+
+impl Default for BytecodeGenerator {
+    fn default() -> Self {
+        BytecodeGenerator::new()
+    }
 }
 
 impl BytecodeGenerator {
@@ -18,9 +50,53 @@ impl BytecodeGenerator {
             instructions: Vec::new(),
             next_register: 0,
             variables: HashMap::new(),
+            functions: HashMap::new(),
+            function_frames: HashMap::new(),
         }
     }
 
+    /// Lowers a function literal. `name`, when given, is registered as callable
+    /// *before* the body is generated, so a call to it from within its own body
+    /// (plain recursion) resolves instead of hitting an undefined-function error.
+    fn generate_function(
+        &mut self,
+        name: Option<&str>,
+        params: &[String],
+        body: &Node,
+    ) -> Result<(Register, usize), GenError> {
+        let skip_jump = self.instructions.len();
+        self.instructions.push(Instruction::Jmp(0));
+        let entry_addr = self.instructions.len();
+
+        if let Some(name) = name {
+            self.functions.insert(name.to_string(), entry_addr);
+        }
+
+        let saved_register = self.next_register;
+        self.next_register = 0;
+
+        let param_regs: Vec<Register> = params.iter().map(|_| self.allocate_register()).collect();
+        for (param, reg) in params.iter().zip(param_regs) {
+            self.instructions
+                .push(Instruction::Store(param.clone(), reg));
+        }
+
+        let result_reg = self.generate(body)?;
+        self.instructions.push(Instruction::Ret(result_reg));
+
+        self.function_frames.insert(entry_addr, self.next_register);
+        self.next_register = saved_register;
+
+        self.instructions[skip_jump] = Instruction::Jmp(self.instructions.len());
+
+        let value_reg = self.allocate_register();
+        self.instructions.push(Instruction::Load(
+            value_reg.clone(),
+            Value::Function(entry_addr),
+        ));
+        Ok((value_reg, entry_addr))
+    }
+
     fn allocate_register(&mut self) -> Register {
         let reg = Register {
             index: self.next_register,
@@ -29,13 +105,13 @@ impl BytecodeGenerator {
         reg
     }
 
-    pub fn generate(&mut self, node: &ASTNode) -> Register {
-        match node {
+    pub fn generate(&mut self, node: &Node) -> Result<Register, GenError> {
+        match &node.kind {
             ASTNode::NumberLiteral(value) => {
                 let reg = self.allocate_register();
                 self.instructions
                     .push(Instruction::Load(reg.clone(), Value::Number(*value)));
-                reg
+                Ok(reg)
             }
             ASTNode::StringLiteral(string) => {
                 let reg = self.allocate_register();
@@ -43,11 +119,11 @@ impl BytecodeGenerator {
                     reg.clone(),
                     Value::String(string.clone()),
                 ));
-                reg
+                Ok(reg)
             }
             ASTNode::BinaryOp(left, op, right) => {
-                let left_reg = self.generate(left);
-                let right_reg = self.generate(right);
+                let left_reg = self.generate(left)?;
+                let right_reg = self.generate(right)?;
                 let result_reg = self.allocate_register();
                 let instruction = match op {
                     BinaryOperator::Add => {
@@ -62,60 +138,161 @@ impl BytecodeGenerator {
                     BinaryOperator::Divide => {
                         Instruction::Div(result_reg.clone(), left_reg, right_reg)
                     }
+                    BinaryOperator::Eq => Instruction::Eq(result_reg.clone(), left_reg, right_reg),
+                    BinaryOperator::Ne => Instruction::Ne(result_reg.clone(), left_reg, right_reg),
+                    BinaryOperator::Lt => Instruction::Lt(result_reg.clone(), left_reg, right_reg),
+                    BinaryOperator::Le => Instruction::Le(result_reg.clone(), left_reg, right_reg),
+                    BinaryOperator::Gt => Instruction::Gt(result_reg.clone(), left_reg, right_reg),
+                    BinaryOperator::Ge => Instruction::Ge(result_reg.clone(), left_reg, right_reg),
+                    BinaryOperator::And => {
+                        Instruction::And(result_reg.clone(), left_reg, right_reg)
+                    }
+                    BinaryOperator::Or => Instruction::Or(result_reg.clone(), left_reg, right_reg),
                 };
                 self.instructions.push(instruction);
-                result_reg
+                Ok(result_reg)
+            }
+            ASTNode::UnaryOp(UnaryOperator::Not, operand) => {
+                let operand_reg = self.generate(operand)?;
+                let result_reg = self.allocate_register();
+                self.instructions
+                    .push(Instruction::Not(result_reg.clone(), operand_reg));
+                Ok(result_reg)
             }
             ASTNode::Variable(name) => {
                 let reg = self.allocate_register();
                 self.instructions
                     .push(Instruction::LoadVar(reg.clone(), name.clone()));
-                reg
+                Ok(reg)
             }
             ASTNode::Assignment(name, value) => {
-                let value_reg = self.generate(value);
+                if let ASTNode::Function(params, body) = &value.kind {
+                    let (value_reg, _entry_addr) =
+                        self.generate_function(Some(name), params, body)?;
+                    self.instructions
+                        .push(Instruction::Store(name.clone(), value_reg.clone()));
+                    self.variables.insert(name.clone(), value_reg.clone());
+                    return Ok(value_reg);
+                }
+                let value_reg = self.generate(value)?;
                 self.instructions
                     .push(Instruction::Store(name.clone(), value_reg.clone()));
                 self.variables.insert(name.clone(), value_reg.clone());
-                value_reg
+                Ok(value_reg)
             }
             ASTNode::Block(statements) => {
                 let mut last_reg = self.allocate_register();
                 for statement in statements {
-                    last_reg = self.generate(statement);
+                    last_reg = self.generate(statement)?;
                 }
-                last_reg
+                Ok(last_reg)
             }
             ASTNode::If(condition, then_branch, else_branch) => {
-                let condition_reg = self.generate(condition);
+                let condition_reg = self.generate(condition)?;
                 let then_label = self.instructions.len();
                 self.instructions
                     .push(Instruction::JmpFalse(condition_reg.clone(), 0));
-                self.generate(then_branch);
-                let end_label = self.instructions.len();
+                // Both branches land their result in `result_reg` via an explicit
+                // `Move`, since only one of them runs and the caller needs a single
+                // register to read the `if`'s value back out of regardless of which
+                // branch was taken.
+                let result_reg = self.allocate_register();
+                let then_reg = self.generate(then_branch)?;
+                self.instructions
+                    .push(Instruction::Move(result_reg.clone(), then_reg));
                 if let Some(else_branch) = else_branch {
                     let else_label = self.instructions.len();
                     self.instructions.push(Instruction::Jmp(0));
                     self.instructions[then_label] =
                         Instruction::JmpFalse(condition_reg.clone(), else_label + 1);
-                    self.generate(else_branch);
+                    let else_reg = self.generate(else_branch)?;
+                    self.instructions
+                        .push(Instruction::Move(result_reg.clone(), else_reg));
                     self.instructions[else_label] = Instruction::Jmp(self.instructions.len());
                 } else {
+                    let end_label = self.instructions.len();
                     self.instructions[then_label] = Instruction::JmpFalse(condition_reg, end_label);
                 }
-                self.allocate_register()
+                Ok(result_reg)
             }
             ASTNode::While(condition, body) => {
                 let loop_start = self.instructions.len();
-                let condition_reg = self.generate(condition);
+                let condition_reg = self.generate(condition)?;
                 let body_start = self.instructions.len();
                 self.instructions
                     .push(Instruction::JmpFalse(condition_reg.clone(), 0)); // reassignment_below
-                self.generate(body);
+                let result_reg = self.allocate_register();
+                let body_reg = self.generate(body)?;
+                self.instructions
+                    .push(Instruction::Move(result_reg.clone(), body_reg));
                 self.instructions.push(Instruction::Jmp(loop_start));
                 let loop_end = self.instructions.len();
                 self.instructions[body_start] = Instruction::JmpFalse(condition_reg, loop_end);
-                self.allocate_register()
+                Ok(result_reg)
+            }
+            ASTNode::Function(params, body) => {
+                let (value_reg, _entry_addr) = self.generate_function(None, params, body)?;
+                Ok(value_reg)
+            }
+            ASTNode::ListLiteral(elements) => {
+                let elem_regs: Vec<Register> = elements
+                    .iter()
+                    .map(|elem| self.generate(elem))
+                    .collect::<Result<_, _>>()?;
+                let dest = self.allocate_register();
+                self.instructions
+                    .push(Instruction::MakeList(dest.clone(), elem_regs));
+                Ok(dest)
+            }
+            ASTNode::Index(list, index) => {
+                let list_reg = self.generate(list)?;
+                let idx_reg = self.generate(index)?;
+                let dest = self.allocate_register();
+                self.instructions
+                    .push(Instruction::Index(dest.clone(), list_reg, idx_reg));
+                Ok(dest)
+            }
+            ASTNode::SetIndex(list, index, value) => {
+                let list_reg = self.generate(list)?;
+                let idx_reg = self.generate(index)?;
+                let value_reg = self.generate(value)?;
+                self.instructions.push(Instruction::SetIndex(
+                    list_reg,
+                    idx_reg,
+                    value_reg.clone(),
+                ));
+                Ok(value_reg)
+            }
+            ASTNode::Call(callee, args) => {
+                let arg_regs: Vec<Register> = args
+                    .iter()
+                    .map(|arg| self.generate(arg))
+                    .collect::<Result<_, _>>()?;
+                let ret_reg = self.allocate_register();
+
+                // A direct, statically-resolved call is just a (cheap) optimization
+                // of the general case below: when the callee is a name already
+                // known to refer to a function, skip loading it into a register and
+                // call its address straight away. Anything else — a function
+                // passed as a parameter, returned from a call, pulled out of a
+                // list, or assigned under a name the generator hasn't seen bind a
+                // function literal — is a value at runtime, so it has to be
+                // resolved through `CallReg` instead.
+                if let ASTNode::Variable(name) = &callee.kind {
+                    if let Some(&addr) = self.functions.get(name) {
+                        self.instructions
+                            .push(Instruction::Call(addr, arg_regs, ret_reg.clone()));
+                        return Ok(ret_reg);
+                    }
+                }
+
+                let callee_reg = self.generate(callee)?;
+                self.instructions.push(Instruction::CallReg(
+                    callee_reg,
+                    arg_regs,
+                    ret_reg.clone(),
+                ));
+                Ok(ret_reg)
             }
         }
     }
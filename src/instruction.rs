@@ -1,22 +1,41 @@
 use crate::value::Value;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Register {
     pub index: usize,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Instruction {
     Halt,
     Load(Register, Value),
+    Move(Register, Register),
     Store(String, Register),
     LoadVar(Register, String),
     Add(Register, Register, Register),
     Sub(Register, Register, Register),
     Mul(Register, Register, Register),
     Div(Register, Register, Register),
+    Eq(Register, Register, Register),
+    Ne(Register, Register, Register),
+    Lt(Register, Register, Register),
+    Le(Register, Register, Register),
+    Gt(Register, Register, Register),
+    Ge(Register, Register, Register),
+    And(Register, Register, Register),
+    Or(Register, Register, Register),
+    Not(Register, Register),
     Jmp(usize),
     JmpFalse(Register, usize),
+    Call(usize, Vec<Register>, Register),
+    CallReg(Register, Vec<Register>, Register),
+    Ret(Register),
+    Yield,
+    Send(Register, Register),
+    Recv(Register),
+    MakeList(Register, Vec<Register>),
+    Index(Register, Register, Register),
+    SetIndex(Register, Register, Register),
     DbgPrintReg(Register),
     DbgPrintVar(String),
 }
@@ -1,9 +1,22 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
 #[derive(Debug, PartialEq, Clone)]
-enum Token {
+pub enum Token {
     Number(f64),
     String(String),
     Identifier(String),
     Equal,
+    EqualEqual,
+    BangEqual,
+    Bang,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    AmpAmp,
+    PipePipe,
     Plus,
     Minus,
     Asterisk,
@@ -12,46 +25,109 @@ enum Token {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Semicolon,
+    Comma,
     If,
     Else,
     While,
     Let,
+    Function,
     EOF,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexError {
+    UnexpectedCharacter(char),
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexError::UnexpectedCharacter(ch) => write!(f, "unexpected character: {}", ch),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
+    pos: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Lexer {
             input: input.chars().peekable(),
+            pos: 0,
         }
     }
 
-    pub fn next_token(&mut self) -> Token {
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.input.next();
+        if let Some(ch) = ch {
+            self.pos += ch.len_utf8();
+        }
+        ch
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, LexError> {
         self.skip_whitespace();
 
-        match self.input.next() {
+        match self.bump() {
             Some(ch) => match ch {
-                '0'..='9' => self.number(ch),
-                '"' => self.string(),
-                'a'..='z' | 'A'..='Z' | '_' => self.identifier(ch),
-                '=' => Token::Equal,
-                '+' => Token::Plus,
-                '-' => Token::Minus,
-                '*' => Token::Asterisk,
-                '/' => Token::Slash,
-                '(' => Token::LeftParen,
-                ')' => Token::RightParen,
-                '{' => Token::LeftBrace,
-                '}' => Token::RightBrace,
-                ';' => Token::Semicolon,
-                _ => panic!("Unexpected character: {}", ch),
+                '0'..='9' => Ok(self.number(ch)),
+                '"' => Ok(self.string()),
+                'a'..='z' | 'A'..='Z' | '_' => Ok(self.identifier(ch)),
+                '=' => Ok(self.one_or_two('=', Token::EqualEqual, Token::Equal)),
+                '!' => Ok(self.one_or_two('=', Token::BangEqual, Token::Bang)),
+                '<' => Ok(self.one_or_two('=', Token::LessEqual, Token::Less)),
+                '>' => Ok(self.one_or_two('=', Token::GreaterEqual, Token::Greater)),
+                '&' => {
+                    if self.input.peek() == Some(&'&') {
+                        self.bump();
+                        Ok(Token::AmpAmp)
+                    } else {
+                        Err(LexError::UnexpectedCharacter('&'))
+                    }
+                }
+                '|' => {
+                    if self.input.peek() == Some(&'|') {
+                        self.bump();
+                        Ok(Token::PipePipe)
+                    } else {
+                        Err(LexError::UnexpectedCharacter('|'))
+                    }
+                }
+                '+' => Ok(Token::Plus),
+                '-' => Ok(Token::Minus),
+                '*' => Ok(Token::Asterisk),
+                '/' => Ok(Token::Slash),
+                '(' => Ok(Token::LeftParen),
+                ')' => Ok(Token::RightParen),
+                '{' => Ok(Token::LeftBrace),
+                '}' => Ok(Token::RightBrace),
+                '[' => Ok(Token::LeftBracket),
+                ']' => Ok(Token::RightBracket),
+                ';' => Ok(Token::Semicolon),
+                ',' => Ok(Token::Comma),
+                _ => Err(LexError::UnexpectedCharacter(ch)),
             },
-            None => Token::EOF,
+            None => Ok(Token::EOF),
+        }
+    }
+
+    fn one_or_two(&mut self, next: char, with_next: Token, without_next: Token) -> Token {
+        if self.input.peek() == Some(&next) {
+            self.bump();
+            with_next
+        } else {
+            without_next
         }
     }
 
@@ -60,16 +136,16 @@ impl<'a> Lexer<'a> {
             if !ch.is_whitespace() {
                 break;
             }
-            self.input.next();
+            self.bump();
         }
     }
 
     fn number(&mut self, first_digit: char) -> Token {
         let mut number = first_digit.to_string();
         while let Some(&ch) = self.input.peek() {
-            if ch.is_digit(10) || ch == '.' {
+            if ch.is_ascii_digit() || ch == '.' {
                 number.push(ch);
-                self.input.next();
+                self.bump();
             } else {
                 break;
             }
@@ -79,7 +155,7 @@ impl<'a> Lexer<'a> {
 
     fn string(&mut self) -> Token {
         let mut string = String::new();
-        while let Some(ch) = self.input.next() {
+        while let Some(ch) = self.bump() {
             if ch == '"' {
                 break;
             }
@@ -93,7 +169,7 @@ impl<'a> Lexer<'a> {
         while let Some(&ch) = self.input.peek() {
             if ch.is_alphanumeric() || ch == '_' {
                 ident.push(ch);
-                self.input.next();
+                self.bump();
             } else {
                 break;
             }
@@ -103,6 +179,7 @@ impl<'a> Lexer<'a> {
             "else" => Token::Else,
             "while" => Token::While,
             "let" => Token::Let,
+            "function" => Token::Function,
             _ => Token::Identifier(ident),
         }
     }
@@ -0,0 +1,10 @@
+pub mod analyzer;
+pub mod ast;
+pub mod generator;
+pub mod instruction;
+pub mod lexer;
+pub mod module;
+pub mod parser;
+pub mod process;
+pub mod value;
+pub mod vm;
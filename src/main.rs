@@ -1,78 +1,66 @@
-mod ast;
-mod generator;
-mod instruction;
-mod process;
-mod value;
-mod vm;
+use es::analyzer::Analyzer;
+use es::ast::{ASTNode, Node, Span};
+use es::generator::BytecodeGenerator;
+use es::instruction::Instruction;
+use es::module::Module;
+use es::parser::Parser;
+use es::vm::ByteCodeVM;
 
-use ast::{ASTNode, BinaryOperator};
-use generator::BytecodeGenerator;
-use instruction::Instruction;
-use vm::ByteCodeVM;
+const SOURCE: &str = r#"
+let x = 0;
+while (x < 5) {
+    x = x + 1;
+    temp = x < 5;
+}
+if (x == 5) {
+    result = 1;
+} else {
+    result = 0;
+}
+let hello = "hello ";
+let world = 38;
+let str = hello + world;
+let square = function(n) {
+    n * n;
+};
+let squared = square(result);
+let numbers = [1, 2, 3];
+let second = numbers[1];
+"#;
 
 fn main() {
     let mut vm = ByteCodeVM::new();
     let process = vm.spawn();
 
-    let ast = ASTNode::Block(vec![
-        ASTNode::Assignment("x".to_string(), Box::new(ASTNode::NumberLiteral(0.0))),
-        ASTNode::While(
-            Box::new(ASTNode::BinaryOp(
-                Box::new(ASTNode::NumberLiteral(5.0)),
-                BinaryOperator::Subtract,
-                Box::new(ASTNode::Variable("x".to_string())),
-            )),
-            Box::new(ASTNode::Block(vec![
-                ASTNode::Assignment(
-                    "x".to_string(),
-                    Box::new(ASTNode::BinaryOp(
-                        Box::new(ASTNode::Variable("x".to_string())),
-                        BinaryOperator::Add,
-                        Box::new(ASTNode::NumberLiteral(1.0)),
-                    )),
-                ),
-                ASTNode::Assignment(
-                    "temp".to_string(),
-                    Box::new(ASTNode::BinaryOp(
-                        Box::new(ASTNode::NumberLiteral(5.0)),
-                        BinaryOperator::Subtract,
-                        Box::new(ASTNode::Variable("x".to_string())),
-                    )),
-                ),
-            ])),
-        ),
-        ASTNode::If(
-            Box::new(ASTNode::BinaryOp(
-                Box::new(ASTNode::Variable("x".to_string())),
-                BinaryOperator::Subtract,
-                Box::new(ASTNode::NumberLiteral(5.0)),
-            )),
-            Box::new(ASTNode::Assignment(
-                "result".to_string(),
-                Box::new(ASTNode::NumberLiteral(1.0)),
-            )),
-            Some(Box::new(ASTNode::Assignment(
-                "result".to_string(),
-                Box::new(ASTNode::NumberLiteral(0.0)),
-            ))),
-        ),
-        ASTNode::Assignment(
-            "hello".to_string(),
-            Box::new(ASTNode::StringLiteral("hello ".to_string())),
-        ),
-        ASTNode::Assignment("world".to_string(), Box::new(ASTNode::NumberLiteral(38.0))),
-        ASTNode::Assignment(
-            "str".to_string(),
-            Box::new(ASTNode::BinaryOp(
-                Box::new(ASTNode::Variable("hello".to_string())),
-                BinaryOperator::Add,
-                Box::new(ASTNode::Variable("world".to_string())),
-            )),
-        ),
-    ]);
+    let mut parser = match Parser::new(SOURCE) {
+        Ok(parser) => parser,
+        Err(err) => {
+            eprintln!("parse error: {}", err);
+            return;
+        }
+    };
+    let statements = match parser.parse_program() {
+        Ok(statements) => statements,
+        Err(err) => {
+            eprintln!("parse error: {}", err);
+            return;
+        }
+    };
+    let diagnostics = Analyzer::analyze(&statements);
+    if !diagnostics.is_empty() {
+        for diagnostic in &diagnostics {
+            eprintln!("analysis error: {}", diagnostic);
+        }
+        return;
+    }
+
+    let program = Node::new(ASTNode::Block(statements), Span::new(0, SOURCE.len()));
 
     let mut generator = BytecodeGenerator::new();
-    generator.generate(&ast);
+    if let Err(err) = generator.generate(&program) {
+        eprintln!("generation error: {}", err);
+        return;
+    }
 
     generator
         .instructions
@@ -83,15 +71,85 @@ fn main() {
     generator
         .instructions
         .push(Instruction::DbgPrintVar("str".to_string()));
+    generator
+        .instructions
+        .push(Instruction::DbgPrintVar("squared".to_string()));
+    generator
+        .instructions
+        .push(Instruction::DbgPrintVar("numbers".to_string()));
+    generator
+        .instructions
+        .push(Instruction::DbgPrintVar("second".to_string()));
 
-    process.load_program(generator.instructions, generator.next_register);
-    match process.run_program() {
-        Ok(_) => {
-            println!("variable states:");
-            for (name, value) in &process.variables {
-                println!("\t {}: {}", name, value);
-            }
+    // Compile once into a `Module`, round-trip it through its binary encoding, and
+    // only then load it into the process, so compilation and execution stay separate.
+    let module = Module::new(
+        generator.instructions,
+        generator.next_register,
+        generator.function_frames,
+    );
+    let mut bytes = Vec::new();
+    if let Err(err) = module.write(&mut bytes) {
+        eprintln!("module write error: {}", err);
+        return;
+    }
+    let module = match Module::read(&mut bytes.as_slice()) {
+        Ok(module) => module,
+        Err(err) => {
+            eprintln!("module read error: {}", err);
+            return;
         }
-        Err(err) => eprintln!("{}", err),
+    };
+    process.load_module(module);
+
+    spawn_ping_pong(&mut vm);
+
+    vm.run();
+
+    println!("variable states:");
+    for (name, value) in vm.process(0).unwrap().variables() {
+        println!("\t {}: {}", name, value);
     }
 }
+
+/// Spawns a pair of processes that pass a number back and forth over their
+/// mailboxes, demonstrating that `ByteCodeVM::run` actually interleaves them.
+fn spawn_ping_pong(vm: &mut ByteCodeVM) {
+    use es::instruction::Register;
+    use es::value::Value;
+
+    let ping = vm.spawn();
+    let ping_pid = ping.pid();
+    let pong_pid = ping_pid + 1;
+    ping.load_program(
+        vec![
+            Instruction::Load(Register { index: 0 }, Value::Number(pong_pid as f64)),
+            Instruction::Load(Register { index: 1 }, Value::Number(1.0)),
+            Instruction::Send(Register { index: 0 }, Register { index: 1 }),
+            Instruction::Yield,
+            Instruction::Recv(Register { index: 2 }),
+            Instruction::DbgPrintReg(Register { index: 2 }),
+            Instruction::Halt,
+        ],
+        3,
+        Default::default(),
+    );
+
+    let pong = vm.spawn();
+    pong.load_program(
+        vec![
+            Instruction::Load(Register { index: 0 }, Value::Number(ping_pid as f64)),
+            Instruction::Recv(Register { index: 1 }),
+            Instruction::Load(Register { index: 2 }, Value::Number(1.0)),
+            Instruction::Add(
+                Register { index: 1 },
+                Register { index: 1 },
+                Register { index: 2 },
+            ),
+            Instruction::Send(Register { index: 0 }, Register { index: 1 }),
+            Instruction::Halt,
+        ],
+        3,
+        Default::default(),
+    );
+}
@@ -0,0 +1,659 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+
+use crate::instruction::{Instruction, Register};
+use crate::value::Value;
+
+const MAGIC: &[u8; 4] = b"ESBC";
+const VERSION: u8 = 1;
+
+const OP_HALT: u8 = 0;
+const OP_LOAD: u8 = 1;
+const OP_STORE: u8 = 2;
+const OP_LOAD_VAR: u8 = 3;
+const OP_ADD: u8 = 4;
+const OP_SUB: u8 = 5;
+const OP_MUL: u8 = 6;
+const OP_DIV: u8 = 7;
+const OP_EQ: u8 = 8;
+const OP_NE: u8 = 9;
+const OP_LT: u8 = 10;
+const OP_LE: u8 = 11;
+const OP_GT: u8 = 12;
+const OP_GE: u8 = 13;
+const OP_AND: u8 = 14;
+const OP_OR: u8 = 15;
+const OP_NOT: u8 = 16;
+const OP_JMP: u8 = 17;
+const OP_JMP_FALSE: u8 = 18;
+const OP_CALL: u8 = 19;
+const OP_CALL_REG: u8 = 30;
+const OP_RET: u8 = 20;
+const OP_YIELD: u8 = 21;
+const OP_SEND: u8 = 22;
+const OP_RECV: u8 = 23;
+const OP_MAKE_LIST: u8 = 24;
+const OP_INDEX: u8 = 25;
+const OP_SET_INDEX: u8 = 26;
+const OP_DBG_PRINT_REG: u8 = 27;
+const OP_DBG_PRINT_VAR: u8 = 28;
+const OP_MOVE: u8 = 29;
+
+const VAL_EMPTY: u8 = 0;
+const VAL_NUMBER: u8 = 1;
+const VAL_BOOLEAN: u8 = 2;
+const VAL_STRING: u8 = 3;
+const VAL_FUNCTION: u8 = 4;
+const VAL_LIST: u8 = 5;
+
+#[derive(Debug)]
+pub enum ModuleError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnknownTag(u8),
+    InvalidUtf8,
+    Truncated,
+}
+
+impl From<io::Error> for ModuleError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => ModuleError::Truncated,
+            _ => ModuleError::Io(err),
+        }
+    }
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModuleError::Io(err) => write!(f, "module io error: {}", err),
+            ModuleError::BadMagic => write!(f, "module error: bad magic header"),
+            ModuleError::UnsupportedVersion(version) => {
+                write!(f, "module error: unsupported version {}", version)
+            }
+            ModuleError::UnknownTag(tag) => write!(f, "module error: unknown tag {}", tag),
+            ModuleError::InvalidUtf8 => write!(f, "module error: invalid utf-8 string"),
+            ModuleError::Truncated => write!(f, "module error: truncated input"),
+        }
+    }
+}
+
+/// A compiled program in a form that can be written to and read back from a
+/// byte stream, so compilation and execution can be separate steps.
+pub struct Module {
+    pub instructions: Vec<Instruction>,
+    pub max_registers: usize,
+    pub function_frames: HashMap<usize, usize>,
+}
+
+impl Module {
+    pub fn new(
+        instructions: Vec<Instruction>,
+        max_registers: usize,
+        function_frames: HashMap<usize, usize>,
+    ) -> Self {
+        Module {
+            instructions,
+            max_registers,
+            function_frames,
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), ModuleError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        write_varint(writer, self.max_registers as u64)?;
+
+        write_varint(writer, self.function_frames.len() as u64)?;
+        for (addr, register_count) in &self.function_frames {
+            write_varint(writer, *addr as u64)?;
+            write_varint(writer, *register_count as u64)?;
+        }
+
+        write_varint(writer, self.instructions.len() as u64)?;
+        for instruction in &self.instructions {
+            write_instruction(writer, instruction)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, ModuleError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ModuleError::BadMagic);
+        }
+
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(ModuleError::UnsupportedVersion(version[0]));
+        }
+
+        let max_registers = read_varint(reader)? as usize;
+
+        let frame_count = read_varint(reader)?;
+        let mut function_frames = HashMap::new();
+        for _ in 0..frame_count {
+            let addr = read_varint(reader)? as usize;
+            let register_count = read_varint(reader)? as usize;
+            function_frames.insert(addr, register_count);
+        }
+
+        let instruction_count = read_varint(reader)?;
+        let mut instructions = Vec::with_capacity(instruction_count as usize);
+        for _ in 0..instruction_count {
+            instructions.push(read_instruction(reader)?);
+        }
+
+        Ok(Module {
+            instructions,
+            max_registers,
+            function_frames,
+        })
+    }
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> Result<(), ModuleError> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            writer.write_all(&[byte])?;
+            return Ok(());
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, ModuleError> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> Result<(), ModuleError> {
+    write_varint(writer, value.len() as u64)?;
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, ModuleError> {
+    let len = read_varint(reader)? as usize;
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| ModuleError::InvalidUtf8)
+}
+
+fn write_register<W: Write>(writer: &mut W, reg: &Register) -> Result<(), ModuleError> {
+    write_varint(writer, reg.index as u64)
+}
+
+fn read_register<R: Read>(reader: &mut R) -> Result<Register, ModuleError> {
+    Ok(Register {
+        index: read_varint(reader)? as usize,
+    })
+}
+
+fn write_registers<W: Write>(writer: &mut W, regs: &[Register]) -> Result<(), ModuleError> {
+    write_varint(writer, regs.len() as u64)?;
+    for reg in regs {
+        write_register(writer, reg)?;
+    }
+    Ok(())
+}
+
+fn read_registers<R: Read>(reader: &mut R) -> Result<Vec<Register>, ModuleError> {
+    let len = read_varint(reader)?;
+    let mut regs = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        regs.push(read_register(reader)?);
+    }
+    Ok(regs)
+}
+
+fn write_value<W: Write>(writer: &mut W, value: &Value) -> Result<(), ModuleError> {
+    match value {
+        Value::Empty => writer.write_all(&[VAL_EMPTY])?,
+        Value::Number(n) => {
+            writer.write_all(&[VAL_NUMBER])?;
+            writer.write_all(&n.to_le_bytes())?;
+        }
+        Value::Boolean(b) => {
+            writer.write_all(&[VAL_BOOLEAN, *b as u8])?;
+        }
+        Value::String(s) => {
+            writer.write_all(&[VAL_STRING])?;
+            write_string(writer, s)?;
+        }
+        Value::Function(addr) => {
+            writer.write_all(&[VAL_FUNCTION])?;
+            write_varint(writer, *addr as u64)?;
+        }
+        Value::List(items) => {
+            writer.write_all(&[VAL_LIST])?;
+            let items = items.borrow();
+            write_varint(writer, items.len() as u64)?;
+            for item in items.iter() {
+                write_value(writer, item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_value<R: Read>(reader: &mut R) -> Result<Value, ModuleError> {
+    let mut tag = [0u8; 1];
+    reader.read_exact(&mut tag)?;
+    match tag[0] {
+        VAL_EMPTY => Ok(Value::Empty),
+        VAL_NUMBER => {
+            let mut bytes = [0u8; 8];
+            reader.read_exact(&mut bytes)?;
+            Ok(Value::Number(f64::from_le_bytes(bytes)))
+        }
+        VAL_BOOLEAN => {
+            let mut byte = [0u8; 1];
+            reader.read_exact(&mut byte)?;
+            Ok(Value::Boolean(byte[0] != 0))
+        }
+        VAL_STRING => Ok(Value::String(read_string(reader)?)),
+        VAL_FUNCTION => Ok(Value::Function(read_varint(reader)? as usize)),
+        VAL_LIST => {
+            let len = read_varint(reader)?;
+            let mut items = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                items.push(read_value(reader)?);
+            }
+            Ok(Value::List(Rc::new(RefCell::new(items))))
+        }
+        other => Err(ModuleError::UnknownTag(other)),
+    }
+}
+
+fn write_instruction<W: Write>(
+    writer: &mut W,
+    instruction: &Instruction,
+) -> Result<(), ModuleError> {
+    match instruction {
+        Instruction::Halt => writer.write_all(&[OP_HALT])?,
+        Instruction::Load(reg, value) => {
+            writer.write_all(&[OP_LOAD])?;
+            write_register(writer, reg)?;
+            write_value(writer, value)?;
+        }
+        Instruction::Move(dest, src) => {
+            writer.write_all(&[OP_MOVE])?;
+            write_register(writer, dest)?;
+            write_register(writer, src)?;
+        }
+        Instruction::Store(name, reg) => {
+            writer.write_all(&[OP_STORE])?;
+            write_string(writer, name)?;
+            write_register(writer, reg)?;
+        }
+        Instruction::LoadVar(reg, name) => {
+            writer.write_all(&[OP_LOAD_VAR])?;
+            write_register(writer, reg)?;
+            write_string(writer, name)?;
+        }
+        Instruction::Add(dest, reg1, reg2) => {
+            writer.write_all(&[OP_ADD])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Sub(dest, reg1, reg2) => {
+            writer.write_all(&[OP_SUB])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Mul(dest, reg1, reg2) => {
+            writer.write_all(&[OP_MUL])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Div(dest, reg1, reg2) => {
+            writer.write_all(&[OP_DIV])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Eq(dest, reg1, reg2) => {
+            writer.write_all(&[OP_EQ])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Ne(dest, reg1, reg2) => {
+            writer.write_all(&[OP_NE])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Lt(dest, reg1, reg2) => {
+            writer.write_all(&[OP_LT])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Le(dest, reg1, reg2) => {
+            writer.write_all(&[OP_LE])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Gt(dest, reg1, reg2) => {
+            writer.write_all(&[OP_GT])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Ge(dest, reg1, reg2) => {
+            writer.write_all(&[OP_GE])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::And(dest, reg1, reg2) => {
+            writer.write_all(&[OP_AND])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Or(dest, reg1, reg2) => {
+            writer.write_all(&[OP_OR])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg1)?;
+            write_register(writer, reg2)?;
+        }
+        Instruction::Not(dest, reg) => {
+            writer.write_all(&[OP_NOT])?;
+            write_register(writer, dest)?;
+            write_register(writer, reg)?;
+        }
+        Instruction::Jmp(dest) => {
+            writer.write_all(&[OP_JMP])?;
+            write_varint(writer, *dest as u64)?;
+        }
+        Instruction::JmpFalse(reg, dest) => {
+            writer.write_all(&[OP_JMP_FALSE])?;
+            write_register(writer, reg)?;
+            write_varint(writer, *dest as u64)?;
+        }
+        Instruction::Call(addr, arg_regs, ret_reg) => {
+            writer.write_all(&[OP_CALL])?;
+            write_varint(writer, *addr as u64)?;
+            write_registers(writer, arg_regs)?;
+            write_register(writer, ret_reg)?;
+        }
+        Instruction::CallReg(callee_reg, arg_regs, ret_reg) => {
+            writer.write_all(&[OP_CALL_REG])?;
+            write_register(writer, callee_reg)?;
+            write_registers(writer, arg_regs)?;
+            write_register(writer, ret_reg)?;
+        }
+        Instruction::Ret(reg) => {
+            writer.write_all(&[OP_RET])?;
+            write_register(writer, reg)?;
+        }
+        Instruction::Yield => writer.write_all(&[OP_YIELD])?,
+        Instruction::Send(pid_reg, val_reg) => {
+            writer.write_all(&[OP_SEND])?;
+            write_register(writer, pid_reg)?;
+            write_register(writer, val_reg)?;
+        }
+        Instruction::Recv(dest) => {
+            writer.write_all(&[OP_RECV])?;
+            write_register(writer, dest)?;
+        }
+        Instruction::MakeList(dest, elem_regs) => {
+            writer.write_all(&[OP_MAKE_LIST])?;
+            write_register(writer, dest)?;
+            write_registers(writer, elem_regs)?;
+        }
+        Instruction::Index(dest, list_reg, idx_reg) => {
+            writer.write_all(&[OP_INDEX])?;
+            write_register(writer, dest)?;
+            write_register(writer, list_reg)?;
+            write_register(writer, idx_reg)?;
+        }
+        Instruction::SetIndex(list_reg, idx_reg, val_reg) => {
+            writer.write_all(&[OP_SET_INDEX])?;
+            write_register(writer, list_reg)?;
+            write_register(writer, idx_reg)?;
+            write_register(writer, val_reg)?;
+        }
+        Instruction::DbgPrintReg(reg) => {
+            writer.write_all(&[OP_DBG_PRINT_REG])?;
+            write_register(writer, reg)?;
+        }
+        Instruction::DbgPrintVar(name) => {
+            writer.write_all(&[OP_DBG_PRINT_VAR])?;
+            write_string(writer, name)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_instruction<R: Read>(reader: &mut R) -> Result<Instruction, ModuleError> {
+    let mut tag = [0u8; 1];
+    reader.read_exact(&mut tag)?;
+    let instruction = match tag[0] {
+        OP_HALT => Instruction::Halt,
+        OP_LOAD => Instruction::Load(read_register(reader)?, read_value(reader)?),
+        OP_MOVE => Instruction::Move(read_register(reader)?, read_register(reader)?),
+        OP_STORE => {
+            let name = read_string(reader)?;
+            Instruction::Store(name, read_register(reader)?)
+        }
+        OP_LOAD_VAR => {
+            let reg = read_register(reader)?;
+            Instruction::LoadVar(reg, read_string(reader)?)
+        }
+        OP_ADD => Instruction::Add(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_SUB => Instruction::Sub(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_MUL => Instruction::Mul(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_DIV => Instruction::Div(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_EQ => Instruction::Eq(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_NE => Instruction::Ne(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_LT => Instruction::Lt(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_LE => Instruction::Le(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_GT => Instruction::Gt(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_GE => Instruction::Ge(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_AND => Instruction::And(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_OR => Instruction::Or(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_NOT => Instruction::Not(read_register(reader)?, read_register(reader)?),
+        OP_JMP => Instruction::Jmp(read_varint(reader)? as usize),
+        OP_JMP_FALSE => {
+            let reg = read_register(reader)?;
+            Instruction::JmpFalse(reg, read_varint(reader)? as usize)
+        }
+        OP_CALL => {
+            let addr = read_varint(reader)? as usize;
+            let arg_regs = read_registers(reader)?;
+            Instruction::Call(addr, arg_regs, read_register(reader)?)
+        }
+        OP_CALL_REG => {
+            let callee_reg = read_register(reader)?;
+            let arg_regs = read_registers(reader)?;
+            Instruction::CallReg(callee_reg, arg_regs, read_register(reader)?)
+        }
+        OP_RET => Instruction::Ret(read_register(reader)?),
+        OP_YIELD => Instruction::Yield,
+        OP_SEND => Instruction::Send(read_register(reader)?, read_register(reader)?),
+        OP_RECV => Instruction::Recv(read_register(reader)?),
+        OP_MAKE_LIST => {
+            let dest = read_register(reader)?;
+            Instruction::MakeList(dest, read_registers(reader)?)
+        }
+        OP_INDEX => Instruction::Index(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_SET_INDEX => Instruction::SetIndex(
+            read_register(reader)?,
+            read_register(reader)?,
+            read_register(reader)?,
+        ),
+        OP_DBG_PRINT_REG => Instruction::DbgPrintReg(read_register(reader)?),
+        OP_DBG_PRINT_VAR => Instruction::DbgPrintVar(read_string(reader)?),
+        other => return Err(ModuleError::UnknownTag(other)),
+    };
+    Ok(instruction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::Register;
+
+    #[test]
+    fn round_trips_every_instruction_through_bytes() {
+        let mut function_frames = HashMap::new();
+        function_frames.insert(4, 2);
+
+        let instructions = vec![
+            Instruction::Load(Register { index: 0 }, Value::Number(1.5)),
+            Instruction::Load(Register { index: 1 }, Value::String("hi".to_string())),
+            Instruction::Move(Register { index: 2 }, Register { index: 1 }),
+            Instruction::Store("x".to_string(), Register { index: 0 }),
+            Instruction::LoadVar(Register { index: 3 }, "x".to_string()),
+            Instruction::MakeList(
+                Register { index: 4 },
+                vec![Register { index: 0 }, Register { index: 1 }],
+            ),
+            Instruction::Index(
+                Register { index: 5 },
+                Register { index: 4 },
+                Register { index: 0 },
+            ),
+            Instruction::SetIndex(
+                Register { index: 4 },
+                Register { index: 0 },
+                Register { index: 1 },
+            ),
+            Instruction::Call(4, vec![Register { index: 0 }], Register { index: 6 }),
+            Instruction::CallReg(
+                Register { index: 6 },
+                vec![Register { index: 0 }],
+                Register { index: 7 },
+            ),
+            Instruction::Ret(Register { index: 6 }),
+            Instruction::Add(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Sub(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Mul(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Div(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Eq(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Ne(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Lt(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Le(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Gt(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Ge(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::And(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Or(Register { index: 2 }, Register { index: 0 }, Register { index: 1 }),
+            Instruction::Not(Register { index: 2 }, Register { index: 0 }),
+            Instruction::Jmp(200),
+            Instruction::JmpFalse(Register { index: 2 }, 3),
+            Instruction::Yield,
+            Instruction::Send(Register { index: 0 }, Register { index: 1 }),
+            Instruction::Recv(Register { index: 3 }),
+            Instruction::DbgPrintReg(Register { index: 3 }),
+            Instruction::DbgPrintVar("x".to_string()),
+            Instruction::Halt,
+        ];
+
+        let module = Module::new(instructions.clone(), 8, function_frames.clone());
+
+        let mut bytes = Vec::new();
+        module.write(&mut bytes).expect("write should succeed");
+
+        let decoded = Module::read(&mut bytes.as_slice()).expect("read should succeed");
+
+        assert_eq!(decoded.max_registers, 8);
+        assert_eq!(decoded.function_frames, function_frames);
+        assert_eq!(decoded.instructions, instructions);
+    }
+
+    #[test]
+    fn rejects_input_with_a_bad_magic_header() {
+        let bytes = [0u8; 8];
+        let result = Module::read(&mut &bytes[..]);
+        assert!(matches!(result, Err(ModuleError::BadMagic)));
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let module = Module::new(vec![Instruction::Halt], 1, HashMap::new());
+        let mut bytes = Vec::new();
+        module.write(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+
+        let result = Module::read(&mut bytes.as_slice());
+        assert!(matches!(result, Err(ModuleError::Truncated)));
+    }
+}
@@ -0,0 +1,435 @@
+use std::fmt;
+
+use crate::{
+    ast::{ASTNode, BinaryOperator, Node, Span, UnaryOperator},
+    lexer::{LexError, Lexer, Token},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    Lex(LexError),
+    UnexpectedToken { expected: String, found: Token },
+    UnexpectedEof,
+}
+
+impl From<LexError> for ParseError {
+    fn from(err: LexError) -> Self {
+        ParseError::Lex(err)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Lex(err) => write!(f, "{}", err),
+            ParseError::UnexpectedToken { expected, found } => {
+                write!(f, "expected {}, found {:?}", expected, found)
+            }
+            ParseError::UnexpectedEof => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    current: Token,
+    current_start: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        let mut lexer = Lexer::new(input);
+        let current_start = lexer.pos();
+        let current = lexer.next_token()?;
+        Ok(Parser {
+            lexer,
+            current,
+            current_start,
+        })
+    }
+
+    pub fn parse_program(&mut self) -> Result<Vec<Node>, ParseError> {
+        let mut statements = Vec::new();
+        while self.current != Token::EOF {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn advance(&mut self) -> Result<Token, ParseError> {
+        self.current_start = self.lexer.pos();
+        let next = self.lexer.next_token()?;
+        Ok(std::mem::replace(&mut self.current, next))
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), ParseError> {
+        if &self.current == expected {
+            self.advance()?;
+            Ok(())
+        } else {
+            Err(ParseError::UnexpectedToken {
+                expected: format!("{:?}", expected),
+                found: self.current.clone(),
+            })
+        }
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        let end = self.current_start;
+        Span::new(start, end.saturating_sub(start))
+    }
+
+    fn statement(&mut self) -> Result<Node, ParseError> {
+        match &self.current {
+            Token::Let => self.let_statement(),
+            Token::If => self.if_statement(),
+            Token::While => self.while_statement(),
+            Token::LeftBrace => self.block_statement(),
+            Token::Identifier(_) => self.assignment_or_expression_statement(),
+            _ => self.expression_statement(),
+        }
+    }
+
+    fn let_statement(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        self.advance()?; // `let`
+        let name = self.identifier_name()?;
+        self.expect(&Token::Equal)?;
+        let value = self.expression()?;
+        self.expect(&Token::Semicolon)?;
+        let span = self.span_from(start);
+        Ok(Node::new(ASTNode::Assignment(name, Box::new(value)), span))
+    }
+
+    fn assignment_or_expression_statement(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let name = match &self.current {
+            Token::Identifier(name) => name.clone(),
+            _ => unreachable!("caller only dispatches here on an identifier"),
+        };
+        let next = self.lexer.clone().next_token()?;
+
+        if next == Token::Equal {
+            self.advance()?; // identifier
+            self.advance()?; // `=`
+            let value = self.expression()?;
+            self.expect(&Token::Semicolon)?;
+            let span = self.span_from(start);
+            return Ok(Node::new(ASTNode::Assignment(name, Box::new(value)), span));
+        }
+
+        self.expression_statement()
+    }
+
+    fn expression_statement(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let node = self.expression()?;
+        if self.current == Token::Equal && matches!(node.kind, ASTNode::Index(..)) {
+            let (list, index) = match node.kind {
+                ASTNode::Index(list, index) => (list, index),
+                _ => unreachable!(),
+            };
+            self.advance()?; // `=`
+            let value = self.expression()?;
+            self.expect(&Token::Semicolon)?;
+            let span = self.span_from(start);
+            return Ok(Node::new(
+                ASTNode::SetIndex(list, index, Box::new(value)),
+                span,
+            ));
+        }
+        if self.current == Token::Semicolon {
+            self.advance()?;
+        }
+        Ok(node)
+    }
+
+    fn if_statement(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        self.advance()?; // `if`
+        self.expect(&Token::LeftParen)?;
+        let condition = self.expression()?;
+        self.expect(&Token::RightParen)?;
+        let then_branch = self.statement()?;
+        let else_branch = if self.current == Token::Else {
+            self.advance()?;
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+        let span = self.span_from(start);
+        Ok(Node::new(
+            ASTNode::If(Box::new(condition), Box::new(then_branch), else_branch),
+            span,
+        ))
+    }
+
+    fn while_statement(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        self.advance()?; // `while`
+        self.expect(&Token::LeftParen)?;
+        let condition = self.expression()?;
+        self.expect(&Token::RightParen)?;
+        let body = self.statement()?;
+        let span = self.span_from(start);
+        Ok(Node::new(
+            ASTNode::While(Box::new(condition), Box::new(body)),
+            span,
+        ))
+    }
+
+    fn block_statement(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        self.advance()?; // `{`
+        let mut statements = Vec::new();
+        while self.current != Token::RightBrace {
+            if self.current == Token::EOF {
+                return Err(ParseError::UnexpectedEof);
+            }
+            statements.push(self.statement()?);
+        }
+        self.advance()?; // `}`
+        let span = self.span_from(start);
+        Ok(Node::new(ASTNode::Block(statements), span))
+    }
+
+    fn identifier_name(&mut self) -> Result<String, ParseError> {
+        match &self.current {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                Ok(name)
+            }
+            other => Err(ParseError::UnexpectedToken {
+                expected: "identifier".to_string(),
+                found: other.clone(),
+            }),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, ParseError> {
+        self.logical_or()
+    }
+
+    fn logical_or(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let mut left = self.logical_and()?;
+        while self.current == Token::PipePipe {
+            self.advance()?;
+            let right = self.logical_and()?;
+            let span = self.span_from(start);
+            left = Node::new(
+                ASTNode::BinaryOp(Box::new(left), BinaryOperator::Or, Box::new(right)),
+                span,
+            );
+        }
+        Ok(left)
+    }
+
+    fn logical_and(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let mut left = self.equality()?;
+        while self.current == Token::AmpAmp {
+            self.advance()?;
+            let right = self.equality()?;
+            let span = self.span_from(start);
+            left = Node::new(
+                ASTNode::BinaryOp(Box::new(left), BinaryOperator::And, Box::new(right)),
+                span,
+            );
+        }
+        Ok(left)
+    }
+
+    fn equality(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let mut left = self.relational()?;
+        loop {
+            let op = match self.current {
+                Token::EqualEqual => BinaryOperator::Eq,
+                Token::BangEqual => BinaryOperator::Ne,
+                _ => break,
+            };
+            self.advance()?;
+            let right = self.relational()?;
+            let span = self.span_from(start);
+            left = Node::new(ASTNode::BinaryOp(Box::new(left), op, Box::new(right)), span);
+        }
+        Ok(left)
+    }
+
+    fn relational(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let mut left = self.additive()?;
+        loop {
+            let op = match self.current {
+                Token::Less => BinaryOperator::Lt,
+                Token::LessEqual => BinaryOperator::Le,
+                Token::Greater => BinaryOperator::Gt,
+                Token::GreaterEqual => BinaryOperator::Ge,
+                _ => break,
+            };
+            self.advance()?;
+            let right = self.additive()?;
+            let span = self.span_from(start);
+            left = Node::new(ASTNode::BinaryOp(Box::new(left), op, Box::new(right)), span);
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = match self.current {
+                Token::Plus => BinaryOperator::Add,
+                Token::Minus => BinaryOperator::Subtract,
+                _ => break,
+            };
+            self.advance()?;
+            let right = self.multiplicative()?;
+            let span = self.span_from(start);
+            left = Node::new(ASTNode::BinaryOp(Box::new(left), op, Box::new(right)), span);
+        }
+        Ok(left)
+    }
+
+    fn multiplicative(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.current {
+                Token::Asterisk => BinaryOperator::Multiply,
+                Token::Slash => BinaryOperator::Divide,
+                _ => break,
+            };
+            self.advance()?;
+            let right = self.unary()?;
+            let span = self.span_from(start);
+            left = Node::new(ASTNode::BinaryOp(Box::new(left), op, Box::new(right)), span);
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        if self.current == Token::Bang {
+            self.advance()?;
+            let operand = self.unary()?;
+            let span = self.span_from(start);
+            return Ok(Node::new(
+                ASTNode::UnaryOp(UnaryOperator::Not, Box::new(operand)),
+                span,
+            ));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        let mut node = self.atom()?;
+        loop {
+            match self.current {
+                Token::LeftParen => {
+                    self.advance()?;
+                    let mut args = Vec::new();
+                    if self.current != Token::RightParen {
+                        loop {
+                            args.push(self.expression()?);
+                            if self.current == Token::Comma {
+                                self.advance()?;
+                            } else {
+                                break;
+                            }
+                        }
+                    }
+                    self.expect(&Token::RightParen)?;
+                    let span = self.span_from(start);
+                    node = Node::new(ASTNode::Call(Box::new(node), args), span);
+                }
+                Token::LeftBracket => {
+                    self.advance()?;
+                    let index = self.expression()?;
+                    self.expect(&Token::RightBracket)?;
+                    let span = self.span_from(start);
+                    node = Node::new(ASTNode::Index(Box::new(node), Box::new(index)), span);
+                }
+                _ => break,
+            }
+        }
+        Ok(node)
+    }
+
+    fn atom(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        match self.current.clone() {
+            Token::Number(value) => {
+                self.advance()?;
+                let span = self.span_from(start);
+                Ok(Node::new(ASTNode::NumberLiteral(value), span))
+            }
+            Token::String(value) => {
+                self.advance()?;
+                let span = self.span_from(start);
+                Ok(Node::new(ASTNode::StringLiteral(value), span))
+            }
+            Token::Identifier(name) => {
+                self.advance()?;
+                let span = self.span_from(start);
+                Ok(Node::new(ASTNode::Variable(name), span))
+            }
+            Token::LeftParen => {
+                self.advance()?;
+                let inner = self.expression()?;
+                self.expect(&Token::RightParen)?;
+                Ok(inner)
+            }
+            Token::Function => self.function_literal(),
+            Token::LeftBracket => self.list_literal(),
+            other => Err(ParseError::UnexpectedToken {
+                expected: "expression".to_string(),
+                found: other,
+            }),
+        }
+    }
+
+    fn list_literal(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        self.advance()?; // `[`
+        let mut elements = Vec::new();
+        if self.current != Token::RightBracket {
+            loop {
+                elements.push(self.expression()?);
+                if self.current == Token::Comma {
+                    self.advance()?;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(&Token::RightBracket)?;
+        let span = self.span_from(start);
+        Ok(Node::new(ASTNode::ListLiteral(elements), span))
+    }
+
+    fn function_literal(&mut self) -> Result<Node, ParseError> {
+        let start = self.current_start;
+        self.advance()?; // `function`
+        self.expect(&Token::LeftParen)?;
+        let mut params = Vec::new();
+        if self.current != Token::RightParen {
+            loop {
+                params.push(self.identifier_name()?);
+                if self.current == Token::Comma {
+                    self.advance()?;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(&Token::RightParen)?;
+        let body = self.block_statement()?;
+        let span = self.span_from(start);
+        Ok(Node::new(ASTNode::Function(params, Box::new(body)), span))
+    }
+}
@@ -1,11 +1,15 @@
+use std::cell::RefCell;
 use std::fmt::{self, Debug};
+use std::rc::Rc;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Empty,
     Number(f64),
     Boolean(bool),
     String(String),
+    Function(usize),
+    List(Rc<RefCell<Vec<Value>>>),
 }
 
 impl fmt::Display for Value {
@@ -15,6 +19,17 @@ impl fmt::Display for Value {
             Value::Boolean(v) => write!(f, "{}", v),
             Value::Number(v) => write!(f, "{}", v),
             Value::String(v) => write!(f, "{}", v),
+            Value::Function(addr) => write!(f, "[function @{}]", addr),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
         }
     }
 }
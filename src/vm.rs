@@ -1,6 +1,9 @@
 use std::fmt;
 
-use crate::process::Process;
+use crate::process::{Process, ProcessStatus};
+
+/// Instructions executed per process before the scheduler moves on to the next one.
+const QUANTUM: usize = 8;
 
 #[derive(Debug)]
 pub enum VMError {
@@ -8,6 +11,8 @@ pub enum VMError {
     DivisionByZero(usize),
     BadAddress(usize),
     UndefinedVariable(usize, String),
+    StackOverflow(usize),
+    IndexOutOfBounds(usize, usize),
 }
 
 impl fmt::Display for VMError {
@@ -19,6 +24,10 @@ impl fmt::Display for VMError {
             VMError::UndefinedVariable(pid, name) => {
                 write!(f, "[process #{}] undefined variable `{}`", pid, name)
             }
+            VMError::StackOverflow(pid) => write!(f, "[process #{}] stack overflow", pid),
+            VMError::IndexOutOfBounds(pid, idx) => {
+                write!(f, "[process #{}] index {} out of bounds", pid, idx)
+            }
         }
     }
 }
@@ -27,6 +36,12 @@ pub struct ByteCodeVM {
     processes: Vec<Process>,
 }
 
+impl Default for ByteCodeVM {
+    fn default() -> Self {
+        ByteCodeVM::new()
+    }
+}
+
 impl ByteCodeVM {
     pub fn new() -> Self {
         ByteCodeVM {
@@ -39,4 +54,46 @@ impl ByteCodeVM {
         self.processes.push(process);
         self.processes.last_mut().unwrap()
     }
+
+    pub fn process(&self, pid: usize) -> Option<&Process> {
+        self.processes.get(pid)
+    }
+
+    /// Round-robins across every spawned process, running up to `QUANTUM`
+    /// instructions per turn, until they have all halted.
+    pub fn run(&mut self) {
+        loop {
+            let mut progressed = false;
+            for pid in 0..self.processes.len() {
+                if self.processes[pid].is_halted() {
+                    continue;
+                }
+                progressed = true;
+                for _ in 0..QUANTUM {
+                    let status = match self.processes[pid].step() {
+                        Ok(status) => status,
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            self.processes[pid].halt();
+                            break;
+                        }
+                    };
+                    match status {
+                        ProcessStatus::Running => continue,
+                        ProcessStatus::Halted | ProcessStatus::Yielded | ProcessStatus::Blocked => {
+                            break
+                        }
+                        ProcessStatus::Sent { to, value } => {
+                            if let Some(target) = self.processes.get_mut(to) {
+                                target.deliver(value);
+                            }
+                        }
+                    }
+                }
+            }
+            if !progressed {
+                break;
+            }
+        }
+    }
 }